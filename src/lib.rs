@@ -8,13 +8,18 @@
 //! which is all this crate is concerned with.
 use std::{
     env,
-    fmt::{self, Display},
+    fmt::{self, Display, Write as _},
     io,
     iter::{Extend, FromIterator, IntoIterator},
     ops::{Add, AddAssign},
-    sync::Once,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
 };
 
+mod write;
+
+use write::AnyWrite;
+
 /// Helper to write escape sequences
 macro_rules! e {
     ($c:tt, $($cn:expr),*) => {
@@ -153,6 +158,90 @@ impl Color {
     }
 }
 
+/// Error returned when a color or style spec string can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid color/style spec: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_hex_rgb(hex: &str) -> Option<Color> {
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+    Some(Color::Rgb(r, g, b))
+}
+
+fn parse_rgb_call(inner: &str) -> Option<Color> {
+    let mut parts = inner.split(',').map(str::trim);
+    let r = parts.next()?.parse().ok()?;
+    let g = parts.next()?.parse().ok()?;
+    let b = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Color::Rgb(r, g, b))
+}
+
+impl FromStr for Color {
+    type Err = ParseError;
+
+    /// Parse a named color (`"red"`, `"bright_black"`), an ANSI 256 index
+    /// (`"fixed(200)"` or a bare `"200"`), or truecolor RGB (`"#RRGGBB"` or
+    /// `"rgb(r, g, b)"`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let lower = trimmed.to_ascii_lowercase();
+        let named = match lower.as_str() {
+            "black" => Some(Color::Black),
+            "red" => Some(Color::Red),
+            "green" => Some(Color::Green),
+            "yellow" => Some(Color::Yellow),
+            "blue" => Some(Color::Blue),
+            "magenta" | "purple" => Some(Color::Magenta),
+            "cyan" => Some(Color::Cyan),
+            "white" => Some(Color::White),
+            "bright_black" => Some(Color::Fixed(8)),
+            "bright_red" => Some(Color::Fixed(9)),
+            "bright_green" => Some(Color::Fixed(10)),
+            "bright_yellow" => Some(Color::Fixed(11)),
+            "bright_blue" => Some(Color::Fixed(12)),
+            "bright_magenta" | "bright_purple" => Some(Color::Fixed(13)),
+            "bright_cyan" => Some(Color::Fixed(14)),
+            "bright_white" => Some(Color::Fixed(15)),
+            _ => None,
+        };
+        if let Some(color) = named {
+            return Ok(color);
+        }
+        if let Some(hex) = lower.strip_prefix('#') {
+            return parse_hex_rgb(hex).ok_or_else(|| ParseError(trimmed.to_string()));
+        }
+        if let Some(inner) = lower.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+            return parse_rgb_call(inner).ok_or_else(|| ParseError(trimmed.to_string()));
+        }
+        if let Some(inner) = lower.strip_prefix("fixed(").and_then(|s| s.strip_suffix(')')) {
+            return inner
+                .trim()
+                .parse()
+                .map(Color::Fixed)
+                .map_err(|_| ParseError(trimmed.to_string()));
+        }
+        if let Ok(n) = lower.parse() {
+            return Ok(Color::Fixed(n));
+        }
+        Err(ParseError(trimmed.to_string()))
+    }
+}
+
 /// Elements that can be added to define a complete `Style`
 ///
 /// Defines the range of possible styles
@@ -168,6 +257,10 @@ pub enum StyleSpec {
     Italic,
     /// Brighter version of color; uses ANSI 256 codes
     Intense,
+    /// Overline text in the terminal; ANSI code 53 equivalent
+    Overline,
+    /// Box/circle frame decoration around the text
+    Frame(Decoration),
     /// Set a foreground color
     Fg(Color),
     /// Set a background color
@@ -176,6 +269,18 @@ pub enum StyleSpec {
     Number(u8),
 }
 
+/// Box/frame decoration for a `Style`, as in delta's `DecorationStyle`.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum Decoration {
+    /// No frame decoration
+    #[default]
+    None,
+    /// Framed text; ANSI code 51 equivalent
+    Box,
+    /// Encircled text; ANSI code 52 equivalent
+    Circle,
+}
+
 /// Defines all aspecs of console text styling
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct Style {
@@ -190,6 +295,8 @@ pub struct Style {
     hidden:        bool,
     strikethrough: bool,
     intense:       bool,
+    overline:      bool,
+    decoration:    Decoration,
 }
 
 impl Add for Style {
@@ -208,6 +315,12 @@ impl Add for Style {
             hidden:        with.hidden || self.hidden,
             strikethrough: with.strikethrough || self.strikethrough,
             intense:       with.intense || self.intense,
+            overline:      with.overline || self.overline,
+            decoration:    if with.decoration != Decoration::None {
+                with.decoration
+            } else {
+                self.decoration
+            },
         }
     }
 }
@@ -229,6 +342,12 @@ impl AddAssign for Style {
             hidden:        with.hidden || self.hidden,
             strikethrough: with.strikethrough || self.strikethrough,
             intense:       with.intense || self.intense,
+            overline:      with.overline || self.overline,
+            decoration:    if with.decoration != Decoration::None {
+                with.decoration
+            } else {
+                self.decoration
+            },
         }
     }
 }
@@ -247,106 +366,178 @@ impl From<Color> for Style {
     }
 }
 
-static mut ALLOWS_COLOR: bool = true;
-static ALLOWS_COLOR_INIT: Once = Once::new();
+/// Write a single code, opening the escape sequence with `\x1b[` on the
+/// first call and separating subsequent ones with `;`.
+fn write_code<W: AnyWrite + ?Sized>(
+    w: &mut W,
+    written_anything: &mut bool,
+    args: fmt::Arguments,
+) -> Result<(), W::Error> {
+    w.write_any_str(if *written_anything { ";" } else { "\x1b[" })?;
+    *written_anything = true;
+    w.write_any_fmt(args)
+}
 
-impl Display for Style {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let w: &mut dyn fmt::Write = f;
-        if !env_allows_color() {
-            return write!(f, "");
+/// Write the full SGR escape sequence for `style` to `w`, coalescing every
+/// active attribute into a single `\x1b[..m` prefix.
+fn write_prefix<W: AnyWrite + ?Sized>(style: &Style, w: &mut W) -> Result<(), W::Error> {
+    let support = color_support();
+    if support == ColorSupport::None {
+        return Ok(());
+    }
+    if style == &Style::default() {
+        return w.write_any_str(e!());
+    }
+
+    let degrade = |color: Color| match (support, color) {
+        (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => Color::Fixed(rgb_to_fixed(r, g, b)),
+        (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => rgb_to_ansi16(r, g, b),
+        (ColorSupport::Ansi16, Color::Fixed(n)) => {
+            let (r, g, b) = fixed_to_rgb(n);
+            rgb_to_ansi16(r, g, b)
         }
-        if self != &Style::default() {
-            if self.bold {
-                write!(w, e!("1"))?;
-            }
-            if self.dimmed {
-                write!(w, e!("2"))?;
-            }
-            if self.italic {
-                write!(w, e!("3"))?;
-            }
-            if self.underline {
-                write!(w, e!("4"))?;
-            }
-            if self.blink {
-                write!(w, e!("5"))?;
-            }
-            if self.reverse {
-                write!(w, e!("7"))?;
+        (_, color) => color,
+    };
+
+    let mut written_anything = false;
+    macro_rules! code {
+        ($($arg:tt)*) => {
+            write_code(w, &mut written_anything, format_args!($($arg)*))?
+        };
+    }
+
+    if style.bold {
+        code!("1");
+    }
+    if style.dimmed {
+        code!("2");
+    }
+    if style.italic {
+        code!("3");
+    }
+    if style.underline {
+        code!("4");
+    }
+    if style.blink {
+        code!("5");
+    }
+    if style.reverse {
+        code!("7");
+    }
+    if style.hidden {
+        code!("8");
+    }
+    if style.strikethrough {
+        code!("9");
+    }
+    if style.overline {
+        code!("53");
+    }
+    match style.decoration {
+        Decoration::None => (),
+        Decoration::Box => code!("51"),
+        Decoration::Circle => code!("52"),
+    }
+    if let Some(fg) = style.fg.map(degrade) {
+        if style.intense && support == ColorSupport::Ansi16 {
+            match fg {
+                Color::Black => code!("90"),
+                Color::Red => code!("91"),
+                Color::Green => code!("92"),
+                Color::Yellow => code!("93"),
+                Color::Blue => code!("94"),
+                Color::Magenta => code!("95"),
+                Color::Cyan => code!("96"),
+                Color::White => code!("97"),
+                Color::Fixed(n) => code!("38;5;{}", n),
+                Color::Rgb(r, g, b) => code!("38;2;{};{};{}", r, g, b),
+                Color::__Nonexhaustive => unreachable!(),
             }
-            if self.hidden {
-                write!(w, e!("8"))?;
+        } else if style.intense {
+            match fg {
+                Color::Black => code!("38;5;8"),
+                Color::Red => code!("38;5;9"),
+                Color::Green => code!("38;5;10"),
+                Color::Yellow => code!("38;5;11"),
+                Color::Blue => code!("38;5;12"),
+                Color::Magenta => code!("38;5;13"),
+                Color::Cyan => code!("38;5;14"),
+                Color::White => code!("38;5;15"),
+                Color::Fixed(n) => code!("38;5;{}", n),
+                Color::Rgb(r, g, b) => code!("38;2;{};{};{}", r, g, b),
+                Color::__Nonexhaustive => unreachable!(),
             }
-            if self.strikethrough {
-                write!(w, e!("9"))?;
+        } else {
+            match fg {
+                Color::Black => code!("30"),
+                Color::Red => code!("31"),
+                Color::Green => code!("32"),
+                Color::Yellow => code!("33"),
+                Color::Blue => code!("34"),
+                Color::Magenta => code!("35"),
+                Color::Cyan => code!("36"),
+                Color::White => code!("37"),
+                Color::Fixed(n) => code!("38;5;{}", n),
+                Color::Rgb(r, g, b) => code!("38;2;{};{};{}", r, g, b),
+                Color::__Nonexhaustive => unreachable!(),
             }
-            if let Some(fg) = self.fg {
-                if self.intense {
-                    match fg {
-                        Color::Black => write!(w, e!("38", "5", "8"))?,
-                        Color::Red => write!(w, e!("38", "5", "9"))?,
-                        Color::Green => write!(w, e!("38", "5", "10"))?,
-                        Color::Yellow => write!(w, e!("38", "5", "11"))?,
-                        Color::Blue => write!(w, e!("38", "5", "12"))?,
-                        Color::Magenta => write!(w, e!("38", "5", "13"))?,
-                        Color::Cyan => write!(w, e!("38", "5", "14"))?,
-                        Color::White => write!(w, e!("38", "5", "15"))?,
-                        Color::Fixed(n) => write!(w, e!("38", "5", "{}"), n)?,
-                        Color::Rgb(r, g, b) => write!(w, e!("38", "2", "{};{};{}"), r, g, b)?,
-                        Color::__Nonexhaustive => unreachable!(),
-                    }
-                } else {
-                    match fg {
-                        Color::Black => write!(w, e!("30"))?,
-                        Color::Red => write!(w, e!("31"))?,
-                        Color::Green => write!(w, e!("32"))?,
-                        Color::Yellow => write!(w, e!("33"))?,
-                        Color::Blue => write!(w, e!("34"))?,
-                        Color::Magenta => write!(w, e!("35"))?,
-                        Color::Cyan => write!(w, e!("36"))?,
-                        Color::White => write!(w, e!("37"))?,
-                        Color::Fixed(n) => write!(w, e!("38", "5", "{}"), n)?,
-                        Color::Rgb(r, g, b) => write!(w, e!("38", "2", "{};{};{}"), r, g, b)?,
-                        Color::__Nonexhaustive => unreachable!(),
-                    }
-                }
+        }
+    }
+    if let Some(bg) = style.bg.map(degrade) {
+        if style.intense && support == ColorSupport::Ansi16 {
+            match bg {
+                Color::Black => code!("100"),
+                Color::Red => code!("101"),
+                Color::Green => code!("102"),
+                Color::Yellow => code!("103"),
+                Color::Blue => code!("104"),
+                Color::Magenta => code!("105"),
+                Color::Cyan => code!("106"),
+                Color::White => code!("107"),
+                Color::Fixed(n) => code!("48;5;{}", n),
+                Color::Rgb(r, g, b) => code!("48;2;{};{};{}", r, g, b),
+                Color::__Nonexhaustive => unreachable!(),
             }
-            if let Some(bg) = self.bg {
-                if self.intense {
-                    match bg {
-                        Color::Black => write!(w, e!("48", "5", "8"))?,
-                        Color::Red => write!(w, e!("48", "5", "9"))?,
-                        Color::Green => write!(w, e!("48", "5", "10"))?,
-                        Color::Yellow => write!(w, e!("48", "5", "11"))?,
-                        Color::Blue => write!(w, e!("48", "5", "12"))?,
-                        Color::Magenta => write!(w, e!("48", "5", "13"))?,
-                        Color::Cyan => write!(w, e!("48", "5", "14"))?,
-                        Color::White => write!(w, e!("48", "5", "15"))?,
-                        Color::Fixed(n) => write!(w, e!("48", "5", "{}"), n)?,
-                        Color::Rgb(r, g, b) => write!(w, e!("48", "2", "{};{};{}"), r, g, b)?,
-                        Color::__Nonexhaustive => unreachable!(),
-                    }
-                } else {
-                    match bg {
-                        Color::Black => write!(w, e!("40"))?,
-                        Color::Red => write!(w, e!("41"))?,
-                        Color::Green => write!(w, e!("42"))?,
-                        Color::Yellow => write!(w, e!("43"))?,
-                        Color::Blue => write!(w, e!("44"))?,
-                        Color::Magenta => write!(w, e!("45"))?,
-                        Color::Cyan => write!(w, e!("46"))?,
-                        Color::White => write!(w, e!("47"))?,
-                        Color::Fixed(n) => write!(w, e!("48", "5", "{}"), n)?,
-                        Color::Rgb(r, g, b) => write!(w, e!("48", "2", "{};{};{}"), r, g, b)?,
-                        Color::__Nonexhaustive => unreachable!(),
-                    }
-                }
+        } else if style.intense {
+            match bg {
+                Color::Black => code!("48;5;8"),
+                Color::Red => code!("48;5;9"),
+                Color::Green => code!("48;5;10"),
+                Color::Yellow => code!("48;5;11"),
+                Color::Blue => code!("48;5;12"),
+                Color::Magenta => code!("48;5;13"),
+                Color::Cyan => code!("48;5;14"),
+                Color::White => code!("48;5;15"),
+                Color::Fixed(n) => code!("48;5;{}", n),
+                Color::Rgb(r, g, b) => code!("48;2;{};{};{}", r, g, b),
+                Color::__Nonexhaustive => unreachable!(),
             }
         } else {
-            write!(w, e!())?;
+            match bg {
+                Color::Black => code!("40"),
+                Color::Red => code!("41"),
+                Color::Green => code!("42"),
+                Color::Yellow => code!("43"),
+                Color::Blue => code!("44"),
+                Color::Magenta => code!("45"),
+                Color::Cyan => code!("46"),
+                Color::White => code!("47"),
+                Color::Fixed(n) => code!("48;5;{}", n),
+                Color::Rgb(r, g, b) => code!("48;2;{};{};{}", r, g, b),
+                Color::__Nonexhaustive => unreachable!(),
+            }
         }
-        Ok(())
+    }
+    if written_anything {
+        w.write_any_str("m")?;
+    }
+    Ok(())
+}
+
+impl Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let w: &mut dyn fmt::Write = f;
+        write_prefix(self, w)
     }
 }
 
@@ -418,6 +609,8 @@ impl Style {
             StyleSpec::Italic => self.italic = true,
             StyleSpec::Intense => self.intense = true,
             StyleSpec::Underline => self.underline = true,
+            StyleSpec::Overline => self.overline = true,
+            StyleSpec::Frame(decoration) => self.decoration = decoration,
             StyleSpec::Reset => *self = Default::default(),
             _ => (),
         }
@@ -433,6 +626,8 @@ impl Style {
             StyleSpec::Italic => self.italic = false,
             StyleSpec::Intense => self.intense = false,
             StyleSpec::Underline => self.underline = false,
+            StyleSpec::Overline => self.overline = false,
+            StyleSpec::Frame(_) => self.decoration = Decoration::None,
             _ => (),
         }
         self
@@ -452,51 +647,248 @@ impl Style {
     }
 }
 
-/// Check environment for signs we shouldn't use color. The first time
-/// this is called, it will check env vars to set global value.
+/// The level of color a terminal supports.
+///
+/// Ordered from least to most capable, so `support >= ColorSupport::Ansi256`
+/// reads naturally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    /// No color; escape sequences should not be emitted at all
+    None,
+    /// Basic/extended 16-color support
+    Ansi16,
+    /// 256-color (8-bit) support
+    Ansi256,
+    /// 24-bit truecolor support
+    TrueColor,
+}
+
+/// Sentinel meaning "not yet detected" in `COLOR_SUPPORT`; `ColorSupport`
+/// only occupies values `0..=3`.
+const UNSET: u8 = u8::MAX;
+
+static COLOR_SUPPORT: AtomicU8 = AtomicU8::new(UNSET);
+
+impl ColorSupport {
+    fn to_u8(self) -> u8 {
+        match self {
+            ColorSupport::None => 0,
+            ColorSupport::Ansi16 => 1,
+            ColorSupport::Ansi256 => 2,
+            ColorSupport::TrueColor => 3,
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => ColorSupport::None,
+            1 => ColorSupport::Ansi16,
+            2 => ColorSupport::Ansi256,
+            _ => ColorSupport::TrueColor,
+        }
+    }
+}
+
+/// Detect color support from `NO_COLOR`, `TERM`, and `COLORTERM`.
+///
+/// `NO_COLOR` set, `TERM` unset, or `TERM=dumb` disable color entirely.
+/// `COLORTERM=truecolor`/`24bit` signals 24-bit support; a `TERM` containing
+/// `256color` signals 8-bit support; anything else capable of `TERM` falls
+/// back to basic 16-color support.
+fn detect_color_support() -> ColorSupport {
+    if env::var_os("NO_COLOR").is_some() {
+        return ColorSupport::None;
+    }
+    let term = match env::var_os("TERM") {
+        None => return ColorSupport::None,
+        Some(term) if term == "dumb" => return ColorSupport::None,
+        Some(term) => term,
+    };
+    if let Some(colorterm) = env::var_os("COLORTERM") {
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorSupport::TrueColor;
+        }
+    }
+    match term.to_str() {
+        Some(term) if term.contains("256color") => ColorSupport::Ansi256,
+        _ => ColorSupport::Ansi16,
+    }
+}
+
+/// The terminal's color support, detected from the environment on first use
+/// (see [`detect_color_support`]) and cached from then on unless overridden
+/// with [`set_color_support`].
+pub fn color_support() -> ColorSupport {
+    match COLOR_SUPPORT.load(Ordering::Relaxed) {
+        UNSET => {
+            let support = detect_color_support();
+            COLOR_SUPPORT.store(support.to_u8(), Ordering::Relaxed);
+            support
+        }
+        encoded => ColorSupport::from_u8(encoded),
+    }
+}
+
+/// Force a color support level at runtime, overriding environment
+/// detection. Intended for a CLI's `--color=always|never|auto` flag.
+pub fn set_color_support(support: ColorSupport) {
+    COLOR_SUPPORT.store(support.to_u8(), Ordering::Relaxed);
+}
+
+/// Check environment for signs we shouldn't use color. Equivalent to
+/// `color_support() != ColorSupport::None`.
 pub fn env_allows_color() -> bool {
-    unsafe {
-        ALLOWS_COLOR_INIT.call_once(|| {
-            // Don't allow color if TERM isn't set or == "dumb"
-            match env::var_os("TERM") {
-                None => ALLOWS_COLOR = false,
-                Some(v) => {
-                    if v == "dumb" {
-                        ALLOWS_COLOR = false;
-                    }
-                }
-            }
-            // Check if NO_COLOR is set
-            if env::var_os("NO_COLOR").is_some() {
-                ALLOWS_COLOR = false;
-            }
-            ALLOWS_COLOR = true;
-        });
-        ALLOWS_COLOR
+    color_support() != ColorSupport::None
+}
+
+/// Convert a truecolor RGB value to the nearest ANSI 256-color palette
+/// index among the color cube and gray ramp (`16..=255`), for terminals
+/// without truecolor support.
+fn rgb_to_fixed(r: u8, g: u8, b: u8) -> u8 {
+    (16..=255u8)
+        .min_by_key(|&n| {
+            let (pr, pg, pb) = fixed_to_rgb(n);
+            let dr = i32::from(r) - i32::from(pr);
+            let dg = i32::from(g) - i32::from(pg);
+            let db = i32::from(b) - i32::from(pb);
+            dr * dr + dg * dg + db * db
+        })
+        .expect("palette range is non-empty")
+}
+
+/// Convert a 256-color palette index back to an approximate RGB value, for
+/// degrading `Color::Fixed` on terminals that don't support it.
+fn fixed_to_rgb(n: u8) -> (u8, u8, u8) {
+    const SYSTEM: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    match n {
+        0..=15 => SYSTEM[n as usize],
+        16..=231 => {
+            let idx = n - 16;
+            let component = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+            (component(idx / 36), component((idx / 6) % 6), component(idx % 6))
+        }
+        gray => {
+            let level = 8 + (gray as u16 - 232) * 10;
+            (level as u8, level as u8, level as u8)
+        }
+    }
+}
+
+/// Convert a truecolor RGB value to the nearest of the 8 basic ANSI colors,
+/// for terminals without 256-color or truecolor support.
+fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    const PALETTE: [(u8, u8, u8, Color); 8] = [
+        (0, 0, 0, Color::Black),
+        (205, 0, 0, Color::Red),
+        (0, 205, 0, Color::Green),
+        (205, 205, 0, Color::Yellow),
+        (0, 0, 238, Color::Blue),
+        (205, 0, 205, Color::Magenta),
+        (0, 205, 205, Color::Cyan),
+        (229, 229, 229, Color::White),
+    ];
+    PALETTE
+        .iter()
+        .min_by_key(|&&(pr, pg, pb, _)| {
+            let dr = i32::from(r) - i32::from(pr);
+            let dg = i32::from(g) - i32::from(pg);
+            let db = i32::from(b) - i32::from(pb);
+            dr * dr + dg * dg + db * db
+        })
+        .map(|&(_, _, _, color)| color)
+        .expect("PALETTE is non-empty")
+}
+
+/// Write only the escape codes needed to go from `prev` to `next`, as
+/// determined by [`Difference::between`].
+fn write_difference<W: AnyWrite + ?Sized>(
+    w: &mut W,
+    prev: &Style,
+    next: &Style,
+) -> Result<(), W::Error> {
+    match Difference::between(prev, next) {
+        Difference::Add(style) => write_prefix(&style, w)?,
+        Difference::Reset => {
+            write_prefix(&Style::reset(), w)?;
+            write_prefix(next, w)?;
+        }
+        Difference::None => (),
     }
+    Ok(())
 }
 
 impl Style {
     /// Write style to io object.
-    pub fn write_to<W: io::Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
-        write!(w, "{}", self)
+    pub fn write_to<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let w: &mut dyn io::Write = w;
+        write_prefix(self, w)
     }
 
     /// Write only difference from prev style
-    pub fn write_difference<W: io::Write + ?Sized>(
-        &self,
-        w: &mut W,
-        prev: &Style,
-    ) -> io::Result<()> {
-        match Difference::between(&prev, &self) {
-            Difference::Add(style) => style.write_to(w)?,
-            Difference::Reset => {
-                Self::reset().write_to(w)?;
-                self.write_to(w)?;
+    pub fn write_difference<W: io::Write>(&self, w: &mut W, prev: &Style) -> io::Result<()> {
+        let w: &mut dyn io::Write = w;
+        write_difference(w, prev, self)
+    }
+}
+
+impl FromStr for Style {
+    type Err = ParseError;
+
+    /// Parse a space- or comma-separated spec, e.g.
+    /// `"bold underline fg=blue bg=#202020"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = Style::default();
+        for token in s.split(|c: char| c.is_whitespace() || c == ',') {
+            if token.is_empty() {
+                continue;
             }
-            Difference::None => (),
-        };
-        Ok(())
+            let lower = token.to_ascii_lowercase();
+            match lower.as_str() {
+                "bold" => style.bold = true,
+                "dimmed" => style.dimmed = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                "blink" => style.blink = true,
+                "reverse" => style.reverse = true,
+                "hidden" => style.hidden = true,
+                "strikethrough" => style.strikethrough = true,
+                "intense" => style.intense = true,
+                "overline" => style.overline = true,
+                _ => {
+                    if let Some(value) = lower.strip_prefix("fg=") {
+                        style.fg = Some(value.parse().map_err(|_| ParseError(token.to_string()))?);
+                    } else if let Some(value) = lower.strip_prefix("bg=") {
+                        style.bg = Some(value.parse().map_err(|_| ParseError(token.to_string()))?);
+                    } else if let Some(value) = lower.strip_prefix("frame=") {
+                        style.decoration = match value {
+                            "box" => Decoration::Box,
+                            "circle" => Decoration::Circle,
+                            _ => return Err(ParseError(token.to_string())),
+                        };
+                    } else {
+                        return Err(ParseError(token.to_string()));
+                    }
+                }
+            }
+        }
+        Ok(style)
     }
 }
 
@@ -541,6 +933,9 @@ impl Difference {
             || (prev.italic && !next.italic)
             || (prev.underline && !next.underline)
             || (prev.intense && !next.intense)
+            || (prev.overline && !next.overline)
+            || (prev.decoration != Decoration::None && next.decoration == Decoration::None)
+            || (prev.decoration != next.decoration && prev.decoration != Decoration::None)
         {
             return Difference::Reset;
         }
@@ -557,30 +952,208 @@ impl Difference {
             hidden:        !prev.hidden && next.hidden,
             strikethrough: !prev.strikethrough && next.strikethrough,
             intense:       !prev.intense && next.intense,
+            overline:      !prev.overline && next.overline,
+            decoration:    if next.decoration != prev.decoration {
+                next.decoration
+            } else {
+                Decoration::None
+            },
         })
     }
 }
 
+/// A run of differently-styled segments, rendered together with only the
+/// minimal escape codes needed between each one.
+///
+/// Printing a `StyledStrings` writes the first segment's full style, then
+/// for every following segment what [`Difference::between`] calls for:
+/// nothing, only the codes that were added, or a reset followed by the full
+/// style when an attribute is dropped. It finishes with a single trailing
+/// reset; an empty run writes nothing. This avoids the redundant reset/re-style pairs that
+/// printing each `Style::paint` segment individually would produce.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StyledStrings(Vec<(Style, String)>);
+
+impl StyledStrings {
+    /// Create an empty run of styled segments
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a styled segment and return `self` for chaining
+    pub fn push<S: Into<String>>(&mut self, style: Style, text: S) -> &mut Self {
+        self.0.push((style, text.into()));
+        self
+    }
+}
+
+impl<S: Into<String>> FromIterator<(Style, S)> for StyledStrings {
+    fn from_iter<I: IntoIterator<Item = (Style, S)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(style, text)| (style, text.into())).collect())
+    }
+}
+
+impl Add for StyledStrings {
+    type Output = Self;
+
+    fn add(mut self, with: Self) -> Self {
+        self.0.extend(with.0);
+        self
+    }
+}
+
+impl AddAssign for StyledStrings {
+    fn add_assign(&mut self, with: Self) {
+        self.0.extend(with.0);
+    }
+}
+
+impl StyledStrings {
+    fn write<W: AnyWrite + ?Sized>(&self, w: &mut W) -> Result<(), W::Error> {
+        let mut segments = self.0.iter();
+        let (first_style, first_text) = match segments.next() {
+            Some(segment) => segment,
+            None => return Ok(()),
+        };
+        write_prefix(first_style, w)?;
+        w.write_any_str(first_text)?;
+
+        let mut prev_style = first_style;
+        for (style, text) in segments {
+            write_difference(w, prev_style, style)?;
+            w.write_any_str(text)?;
+            prev_style = style;
+        }
+        write_prefix(&Style::reset(), w)
+    }
+
+    /// Write the run to an io object, emitting only the difference between
+    /// consecutive segment styles.
+    pub fn write_to<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let w: &mut dyn io::Write = w;
+        self.write(w)
+    }
+}
+
+impl Display for StyledStrings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let w: &mut dyn fmt::Write = f;
+        self.write(w)
+    }
+}
+
+/// Linearly interpolate between two `u8` values at position `t` (0.0..=1.0).
+fn lerp(a: u8, b: u8, t: f32) -> u8 {
+    (a as f32 + (b as f32 - a as f32) * t).round() as u8
+}
+
+type RgbEndpoints = ((u8, u8, u8), (u8, u8, u8));
+
+/// Paints a string with a foreground (and optionally background) color that
+/// interpolates between two RGB endpoints, one step per character.
+///
+/// Useful for banner/heading effects without precomputing a color table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Gradient {
+    start: (u8, u8, u8),
+    end:   (u8, u8, u8),
+    bg:    Option<RgbEndpoints>,
+}
+
+impl Gradient {
+    /// Create a gradient that interpolates the foreground color from
+    /// `start` to `end`.
+    pub fn new(start: (u8, u8, u8), end: (u8, u8, u8)) -> Self {
+        Self { start, end, bg: None }
+    }
+
+    /// Additionally interpolate the background color from `start` to `end`.
+    pub fn bg(mut self, start: (u8, u8, u8), end: (u8, u8, u8)) -> Self {
+        self.bg = Some((start, end));
+        self
+    }
+
+    /// Paint `input`, giving character `i` of `n` visible characters the
+    /// color at `t = i / (n - 1).max(1)` along the gradient. An empty
+    /// string produces no output; a single character uses `start`.
+    pub fn paint<S: AsRef<str>>(&self, input: S) -> String {
+        let input = input.as_ref();
+        let n = input.chars().count();
+        if n == 0 {
+            return String::new();
+        }
+        let denom = (n - 1).max(1) as f32;
+
+        let mut out = String::new();
+        for (i, ch) in input.chars().enumerate() {
+            let t = i as f32 / denom;
+            let mut style = Style::from_fg(Color::Rgb(
+                lerp(self.start.0, self.end.0, t),
+                lerp(self.start.1, self.end.1, t),
+                lerp(self.start.2, self.end.2, t),
+            ));
+            if let Some((bg_start, bg_end)) = self.bg {
+                style.bg(Some(Color::Rgb(
+                    lerp(bg_start.0, bg_end.0, t),
+                    lerp(bg_start.1, bg_end.1, t),
+                    lerp(bg_start.2, bg_end.2, t),
+                )));
+            }
+            write!(out, "{}", style).unwrap();
+            out.push(ch);
+        }
+        write!(out, "{}", Style::reset()).unwrap();
+        out
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{io::Write, str};
+    use std::{
+        io::Write,
+        str,
+        sync::Mutex,
+    };
     use Color::*;
 
+    /// Serializes access to the process-global `COLOR_SUPPORT` atomic.
+    /// `cargo test` runs tests on multiple threads by default, and any test
+    /// that reads or writes color support would otherwise race with every
+    /// other such test.
+    static COLOR_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Run `f` with color support forced to `level` for the duration, under
+    /// `COLOR_TEST_LOCK`, restoring the previous level afterwards. Keeps
+    /// escape-sequence assertions hermetic regardless of the ambient
+    /// `TERM`/`COLORTERM`/`NO_COLOR` environment.
+    fn with_color_support<T>(level: ColorSupport, f: impl FnOnce() -> T) -> T {
+        let _guard = COLOR_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original = color_support();
+        set_color_support(level);
+        let result = f();
+        set_color_support(original);
+        result
+    }
+
     macro_rules! test {
         ($name: ident, $style: expr, $input: expr => $result: expr) => {
             #[test]
             fn $name() {
-                let mut buf: Vec<u8> = vec![];
-                $style.write_to(&mut buf).unwrap();
-                write!(buf, $input).unwrap();
-                assert_eq!(str::from_utf8(&buf).unwrap(), $result);
+                with_color_support(ColorSupport::TrueColor, || {
+                    let mut buf: Vec<u8> = vec![];
+                    $style.write_to(&mut buf).unwrap();
+                    write!(buf, $input).unwrap();
+                    assert_eq!(str::from_utf8(&buf).unwrap(), $result);
+                });
             }
         };
         ($name: ident, $style: expr => $result: expr) => {
             #[test]
             fn $name() {
-                assert_eq!($style.to_string(), $result.to_string());
+                with_color_support(ColorSupport::TrueColor, || {
+                    assert_eq!($style.to_string(), $result.to_string());
+                });
             }
         };
         ($name: ident, $style: expr, $result: expr) => {
@@ -592,7 +1165,136 @@ mod tests {
     }
 
     test!(ansi_write_256, Style::default(), "text/plain" => "\x1b[0mtext/plain");
+    #[test]
+    fn write_to_matches_display() {
+        with_color_support(ColorSupport::TrueColor, || {
+            let mut style = Cyan.on(Red);
+            style.bold(true);
+            let mut buf: Vec<u8> = vec![];
+            style.write_to(&mut buf).unwrap();
+            assert_eq!(str::from_utf8(&buf).unwrap(), style.to_string());
+        });
+    }
+    test!(rgb_to_fixed_black, rgb_to_fixed(0, 0, 0), 16);
+    test!(rgb_to_fixed_white, rgb_to_fixed(255, 255, 255), 231);
+    test!(rgb_to_fixed_red, rgb_to_fixed(255, 0, 0), 16 + 36 * 5);
+    #[test]
+    fn truecolor_degrades_to_fixed_on_ansi256() {
+        with_color_support(ColorSupport::Ansi256, || {
+            let style = Rgb(10, 20, 30).normal();
+            assert_eq!(style.to_string(), format!("\x1b[38;5;{}m", rgb_to_fixed(10, 20, 30)));
+        });
+    }
+    #[test]
+    fn truecolor_degrades_to_ansi16() {
+        with_color_support(ColorSupport::Ansi16, || {
+            let style = Rgb(200, 10, 10).normal();
+            assert_eq!(style.to_string(), Red.normal().to_string());
+        });
+    }
+    #[test]
+    fn fixed_degrades_to_ansi16() {
+        with_color_support(ColorSupport::Ansi16, || {
+            let style = Color::Fixed(46).normal(); // pure green in the 256-color cube
+            assert_eq!(style.to_string(), Green.normal().to_string());
+        });
+    }
+    #[test]
+    fn intense_truecolor_degrades_to_bright_ansi16() {
+        with_color_support(ColorSupport::Ansi16, || {
+            let style = Rgb(200, 10, 10).intense();
+            assert_eq!(style.to_string(), "\x1b[91m");
+        });
+    }
+    #[test]
+    fn color_support_override_round_trips() {
+        let _guard = COLOR_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original = color_support();
+        set_color_support(ColorSupport::TrueColor);
+        assert_eq!(color_support(), ColorSupport::TrueColor);
+        set_color_support(original);
+    }
+    #[test]
+    fn styled_strings_minimal_difference() {
+        with_color_support(ColorSupport::TrueColor, || {
+            let strings: StyledStrings = vec![(Red.bold(), "a"), (Red.normal(), "b"), (Blue.normal(), "c")]
+                .into_iter()
+                .collect();
+            assert_eq!(strings.to_string(), "\x1b[1;31ma\x1b[0m\x1b[31mb\x1b[34mc\x1b[0m");
+        });
+    }
+    #[test]
+    fn gradient_endpoints() {
+        with_color_support(ColorSupport::TrueColor, || {
+            let gradient = Gradient::new((0, 0, 0), (100, 200, 255));
+            assert_eq!(gradient.paint(""), "");
+            assert_eq!(
+                gradient.paint("x"),
+                format!("{}x{}", Rgb(0, 0, 0).normal(), Style::reset())
+            );
+            assert_eq!(
+                gradient.paint("ab"),
+                format!(
+                    "{}a{}b{}",
+                    Rgb(0, 0, 0).normal(),
+                    Rgb(100, 200, 255).normal(),
+                    Style::reset()
+                )
+            );
+        });
+    }
+    #[test]
+    fn intense_without_fg_emits_nothing() {
+        with_color_support(ColorSupport::TrueColor, || {
+            let mut style = Style::default();
+            style.add_spec(StyleSpec::Intense);
+            assert_eq!(style.to_string(), "");
+        });
+    }
     test!(intense, Cyan.intense() => "\x1b[38;5;14m");
+    test!(
+        overline_and_frame,
+        {
+            let mut s = Style::from_fg(Red);
+            s.add_spec(StyleSpec::Overline);
+            s.add_spec(StyleSpec::Frame(Decoration::Box));
+            s
+        } => "\x1b[53;51;31m"
+    );
+    #[test]
+    fn frame_removed_triggers_reset() {
+        let mut boxed = Style::from_fg(Blue);
+        boxed.add_spec(StyleSpec::Frame(Decoration::Box));
+        assert!(matches!(
+            Difference::between(&boxed, &Blue.normal()),
+            Difference::Reset
+        ));
+    }
+    #[test]
+    fn frame_changed_triggers_reset() {
+        let mut boxed = Style::from_fg(Blue);
+        boxed.add_spec(StyleSpec::Frame(Decoration::Box));
+        let mut circled = boxed;
+        circled.add_spec(StyleSpec::Frame(Decoration::Circle));
+        assert!(matches!(Difference::between(&boxed, &circled), Difference::Reset));
+    }
+    test!(color_from_str_named, "Bright_Red".parse(), Ok(Fixed(9)));
+    test!(color_from_str_hex, "#2a3b4c".parse(), Ok(Rgb(0x2a, 0x3b, 0x4c)));
+    test!(color_from_str_rgb_call, "rgb(1, 2, 3)".parse(), Ok(Rgb(1, 2, 3)));
+    test!(color_from_str_fixed_call, "fixed(200)".parse(), Ok(Fixed(200)));
+    test!(color_from_str_bare_number, "200".parse(), Ok(Fixed(200)));
+    test!(color_from_str_invalid, "not_a_color".parse::<Color>().is_err(), true);
+    test!(
+        style_from_str,
+        "bold underline fg=blue bg=#202020".parse(),
+        Ok(Style {
+            fg: Some(Blue),
+            bg: Some(Rgb(0x20, 0x20, 0x20)),
+            bold: true,
+            underline: true,
+            ..Style::default()
+        })
+    );
     test!(
         remove_fg,
         Blue.normal().remove(StyleSpec::Fg(Blue)),
@@ -600,7 +1302,11 @@ mod tests {
     );
     test!(unset_bg, Style::from_bg(Blue).bg(None), Style::default());
     test!(rgb, Rgb(254, 253, 255).normal() => "\x1b[38;2;254;253;255m");
-    test!(bold, White.bold() => "\x1b[1m\x1b[37m");
+    test!(
+        coalesced_attributes,
+        { let mut s = Blue.on(Red); s.bold(true); s.underline(true); s } => "\x1b[1;4;34;41m"
+    );
+    test!(bold, White.bold() => "\x1b[1;37m");
     test!(
         stylespec_into_style,
         Into::<Style>::into(StyleSpec::Fg(Red)),
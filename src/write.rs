@@ -0,0 +1,46 @@
+//! An abstraction over `std::io::Write` and `std::fmt::Write`.
+//!
+//! Styles need to be serialized both into `String` buffers (via `Display`) and
+//! directly onto `io::Write` sinks (via `write_to`). Rather than duplicate the
+//! escape-sequence logic for each, every write goes through `AnyWrite`, which
+//! is implemented for `dyn io::Write` and `dyn fmt::Write` so a single routine
+//! can target either.
+use std::fmt;
+use std::io;
+
+/// A shared interface for `io::Write` and `fmt::Write`, so escape sequences
+/// only need to be generated once.
+pub(crate) trait AnyWrite {
+    /// The error type this write might return.
+    type Error;
+
+    /// Write a string slice.
+    fn write_any_str(&mut self, s: &str) -> Result<(), Self::Error>;
+
+    /// Write a format string, as produced by `format_args!`.
+    fn write_any_fmt(&mut self, fmt: fmt::Arguments) -> Result<(), Self::Error>;
+}
+
+impl<'a> AnyWrite for dyn fmt::Write + 'a {
+    type Error = fmt::Error;
+
+    fn write_any_str(&mut self, s: &str) -> fmt::Result {
+        self.write_str(s)
+    }
+
+    fn write_any_fmt(&mut self, fmt: fmt::Arguments) -> fmt::Result {
+        self.write_fmt(fmt)
+    }
+}
+
+impl<'a> AnyWrite for dyn io::Write + 'a {
+    type Error = io::Error;
+
+    fn write_any_str(&mut self, s: &str) -> io::Result<()> {
+        self.write_all(s.as_bytes())
+    }
+
+    fn write_any_fmt(&mut self, fmt: fmt::Arguments) -> io::Result<()> {
+        self.write_fmt(fmt)
+    }
+}